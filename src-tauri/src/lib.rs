@@ -1,33 +1,93 @@
 mod rmbg;
+mod sam;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use image::ImageFormat::Png;
-use rmbg::Rmbg;
+use rmbg::{CompositeTarget, Rmbg};
+use sam::{Prompt, Sam};
 use std::io::Cursor;
-use tauri::{path::BaseDirectory, Manager};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tauri::{path::BaseDirectory, Manager, State};
+
+/// Locks `mutex`, recovering a poisoned guard instead of panicking. A panic from one bad
+/// input (an unreadable path, a corrupt image) shouldn't brick every later call that shares
+/// this managed state.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
-fn remove_bg_from_select(handle: tauri::AppHandle, path: String) -> String {
-    let resource_path = handle
-        .path()
-        .resolve("resources/rmbg.onnx", BaseDirectory::Resource)
-        .unwrap();
-
-    let rmbg = Rmbg::new(resource_path).unwrap();
+fn remove_bg_from_select(state: State<Mutex<Rmbg>>, path: String) -> Result<String, String> {
+    let rmbg = lock(&state);
 
     // Load an image
-    let original_img = image::open(path).unwrap();
+    let original_img = image::open(path).map_err(|e| e.to_string())?;
 
     // Remove the background
-    let img_without_bg = rmbg.remove_background(&original_img).unwrap();
+    let img_without_bg = rmbg
+        .remove_background(&original_img)
+        .map_err(|e| e.to_string())?;
+
+    encode_png_base64(&img_without_bg)
+}
+
+#[tauri::command]
+fn remove_bg_from_batch(
+    state: State<Mutex<Rmbg>>,
+    paths: Vec<String>,
+) -> Vec<Result<String, String>> {
+    let rmbg = lock(&state);
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let original_img = image::open(path).map_err(|e| e.to_string())?;
+            let img_without_bg = rmbg
+                .remove_background(&original_img)
+                .map_err(|e| e.to_string())?;
+            encode_png_base64(&img_without_bg)
+        })
+        .collect()
+}
+
+#[tauri::command]
+fn replace_bg_from_select(
+    state: State<Mutex<Rmbg>>,
+    path: String,
+    target: CompositeTarget,
+) -> Result<String, String> {
+    let rmbg = lock(&state);
 
+    let original_img = image::open(path).map_err(|e| e.to_string())?;
+    let composited = rmbg
+        .replace_background(&original_img, &target)
+        .map_err(|e| e.to_string())?;
+
+    encode_png_base64(&composited)
+}
+
+#[tauri::command]
+fn segment_from_prompt(
+    state: State<Mutex<Sam>>,
+    path: String,
+    prompt: Prompt,
+) -> Result<String, String> {
+    let sam = lock(&state);
+
+    let original_img = image::open(path).map_err(|e| e.to_string())?;
+    let segmented = sam
+        .segment_with_prompt(&original_img, &prompt)
+        .map_err(|e| e.to_string())?;
+
+    encode_png_base64(&segmented)
+}
+
+fn encode_png_base64(img: &image::DynamicImage) -> Result<String, String> {
     let mut image_data: Vec<u8> = Vec::new();
-    img_without_bg
-        .write_to(&mut Cursor::new(&mut image_data), Png)
-        .unwrap();
+    img.write_to(&mut Cursor::new(&mut image_data), Png)
+        .map_err(|e| e.to_string())?;
     // turn to bytes
-    let b64 = STANDARD.encode(image_data);
-    return b64;
+    Ok(STANDARD.encode(image_data))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -35,7 +95,30 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![remove_bg_from_select])
+        .setup(|app| {
+            let resource_path = app
+                .path()
+                .resolve("resources/rmbg.onnx", BaseDirectory::Resource)?;
+            let rmbg = Rmbg::new(resource_path)?;
+            app.manage(Mutex::new(rmbg));
+
+            let sam_encoder_path = app
+                .path()
+                .resolve("resources/sam_encoder.onnx", BaseDirectory::Resource)?;
+            let sam_decoder_path = app
+                .path()
+                .resolve("resources/sam_decoder.onnx", BaseDirectory::Resource)?;
+            let sam = Sam::new(sam_encoder_path, sam_decoder_path)?;
+            app.manage(Mutex::new(sam));
+
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            remove_bg_from_select,
+            remove_bg_from_batch,
+            replace_bg_from_select,
+            segment_from_prompt
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
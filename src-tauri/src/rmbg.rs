@@ -1,13 +1,39 @@
 use anyhow::anyhow;
 use fast_image_resize::{self as fr, IntoImageViewMut};
-use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, Rgba, RgbaImage};
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, RgbImage, Rgba, RgbaImage};
 use ndarray::{s, Array3, ArrayView, Axis, Dim};
+use serde::Deserialize;
 use std::path::Path;
 
-const ML_MODEL_IMAGE_WIDTH: u32 = 1024;
-const ML_MODEL_IMAGE_HEIGHT: u32 = 1024;
-const ML_MODEL_INPUT_NAME: &str = "input";
-const ML_MODEL_OUTPUT_NAME: &str = "output";
+/// How a model expects its input normalized and named, so `Rmbg` isn't hardcoded to the
+/// bundled RMBG network. See [`ModelConfig::rmbg_defaults`] for the values that model uses.
+pub struct ModelConfig {
+    /// The size the model expects its input letterboxed to, as `(width, height)`.
+    pub input_size: (u32, u32),
+    /// Per-channel mean subtracted from each RGB channel after scaling pixels to `[0, 1]`.
+    pub mean: [f32; 3],
+    /// Per-channel standard deviation each RGB channel is divided by after mean subtraction.
+    pub std: [f32; 3],
+    /// The name of the model's input tensor.
+    pub input_name: String,
+    /// The name of the model's output tensor.
+    pub output_name: String,
+}
+
+impl ModelConfig {
+    /// The bundled `rmbg.onnx` model's configuration: a 1024x1024 input, a 0.5 mean / 1.0 std
+    /// scalar normalization applied identically across all three channels, and "input"/"output"
+    /// tensor names.
+    pub fn rmbg_defaults() -> Self {
+        ModelConfig {
+            input_size: (1024, 1024),
+            mean: [0.5, 0.5, 0.5],
+            std: [1.0, 1.0, 1.0],
+            input_name: "input".to_string(),
+            output_name: "output".to_string(),
+        }
+    }
+}
 
 /// A struct for removing backgrounds from images using a machine learning model.
 ///
@@ -16,6 +42,66 @@ const ML_MODEL_OUTPUT_NAME: &str = "output";
 /// from given images.
 pub struct Rmbg {
     model: ort::Session,
+    resize_filter: fr::FilterType,
+    linear_light_resize: bool,
+    config: ModelConfig,
+}
+
+/// Records how an image was letterboxed into the model's square input canvas, so the
+/// resulting mask can be cropped back to the real image region before it is upscaled.
+pub(crate) struct Letterbox {
+    pub(crate) scale: f32,
+    pub(crate) pad_x: u32,
+    pub(crate) pad_y: u32,
+    pub(crate) scaled_width: u32,
+    pub(crate) scaled_height: u32,
+}
+
+/// Where the scaled image is placed within the padded canvas.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LetterboxAnchor {
+    /// Center the scaled image, padding evenly on both sides — what RMBG-style matting nets
+    /// expect.
+    Center,
+    /// Anchor the scaled image to the top-left corner, padding only the bottom/right — the
+    /// convention the official Segment Anything ONNX export's `ResizeLongestSide`
+    /// preprocessing uses.
+    TopLeft,
+}
+
+/// Computes the letterbox scale and pad offsets for fitting a `width` x `height` image into a
+/// `target_width` x `target_height` canvas without distorting it. Shared between preprocessing
+/// (to know where to place the resized pixels) and prompt-based segmentation (to map prompt
+/// coordinates into the same model space).
+pub(crate) fn compute_letterbox(
+    width: u32,
+    height: u32,
+    target_width: u32,
+    target_height: u32,
+    anchor: LetterboxAnchor,
+) -> Letterbox {
+    // Scale against whichever axis is tighter, so the scaled image never exceeds either
+    // target dimension — required for this to stay correct when `target_width` and
+    // `target_height` differ (a non-square model input).
+    let scale =
+        (target_width as f32 / width as f32).min(target_height as f32 / height as f32);
+    let scaled_width = (width as f32 * scale).round() as u32;
+    let scaled_height = (height as f32 * scale).round() as u32;
+    let (pad_x, pad_y) = match anchor {
+        LetterboxAnchor::Center => (
+            (target_width - scaled_width) / 2,
+            (target_height - scaled_height) / 2,
+        ),
+        LetterboxAnchor::TopLeft => (0, 0),
+    };
+
+    Letterbox {
+        scale,
+        pad_x,
+        pad_y,
+        scaled_width,
+        scaled_height,
+    }
 }
 
 impl Rmbg {
@@ -41,8 +127,70 @@ impl Rmbg {
     /// let rmbg = Rmbg::new("path/to/model.onnx").expect("Failed to load model");
     /// ```
     pub fn new(model_path: impl AsRef<Path>) -> Result<Self, ort::Error> {
+        Self::new_with_options(
+            model_path,
+            fr::FilterType::Bilinear,
+            false,
+            ModelConfig::rmbg_defaults(),
+        )
+    }
+
+    /// Constructs a new `Rmbg` instance, like [`Rmbg::new`], but lets the caller pick the
+    /// resampling filter used whenever an image is resized (both the downscale into the
+    /// model's input canvas and the mask upscale back to the original dimensions).
+    ///
+    /// Softer filters such as `Bilinear` are cheaper; `CatmullRom` and `Lanczos3` keep more
+    /// detail on downscale at the cost of a slower resize.
+    pub fn new_with_filter(
+        model_path: impl AsRef<Path>,
+        resize_filter: fr::FilterType,
+    ) -> Result<Self, ort::Error> {
+        Self::new_with_options(
+            model_path,
+            resize_filter,
+            false,
+            ModelConfig::rmbg_defaults(),
+        )
+    }
+
+    /// Constructs a new `Rmbg` instance for a model other than the bundled RMBG network, using
+    /// `config` for its input size, normalization, and tensor names instead of the RMBG defaults.
+    pub fn new_with_config(
+        model_path: impl AsRef<Path>,
+        config: ModelConfig,
+    ) -> Result<Self, ort::Error> {
+        Self::new_with_options(model_path, fr::FilterType::Bilinear, false, config)
+    }
+
+    /// Constructs a new `Rmbg` instance, like [`Rmbg::new`], but resizes in linear light
+    /// instead of directly in sRGB space. This avoids the darkened edges and haloing that
+    /// blending gamma-encoded pixels produces around high-contrast cut-out boundaries, at the
+    /// cost of a sRGB<->linear conversion pass on every resize.
+    pub fn new_with_linear_light_resize(
+        model_path: impl AsRef<Path>,
+        linear_light_resize: bool,
+    ) -> Result<Self, ort::Error> {
+        Self::new_with_options(
+            model_path,
+            fr::FilterType::Bilinear,
+            linear_light_resize,
+            ModelConfig::rmbg_defaults(),
+        )
+    }
+
+    fn new_with_options(
+        model_path: impl AsRef<Path>,
+        resize_filter: fr::FilterType,
+        linear_light_resize: bool,
+        config: ModelConfig,
+    ) -> Result<Self, ort::Error> {
         let model = ort::Session::builder()?.commit_from_file(model_path)?;
-        Ok(Rmbg { model })
+        Ok(Rmbg {
+            model,
+            resize_filter,
+            linear_light_resize,
+            config,
+        })
     }
 
     /// Removes the background from a given image using the loaded machine learning model.
@@ -69,21 +217,38 @@ impl Rmbg {
     /// let img_without_bg = rmbg.remove_background(&original_img).expect("Failed to remove background");
     /// ```
     pub fn remove_background(&self, original_img: &DynamicImage) -> anyhow::Result<DynamicImage> {
-        let img = preprocess_image(original_img)?;
+        let (img, letterbox) = preprocess_image(
+            original_img,
+            self.resize_filter,
+            self.linear_light_resize,
+            &self.config,
+        )?;
 
         let input = img.insert_axis(Axis(0));
-        let inputs = ort::inputs![ML_MODEL_INPUT_NAME => input.view()]?;
+        let inputs = ort::inputs![self.config.input_name.as_str() => input.view()]?;
 
         let outputs = self.model.run(inputs)?;
 
-        let output = outputs[ML_MODEL_OUTPUT_NAME].try_extract_tensor()?;
+        let output = outputs[self.config.output_name.as_str()].try_extract_tensor()?;
         let view = output.view();
         let output: ArrayView<f32, Dim<[usize; 2]>> = view.slice(s![0, 0, .., ..]);
 
         let image = postprocess_image(&output)?;
+        let image = image.crop_imm(
+            letterbox.pad_x,
+            letterbox.pad_y,
+            letterbox.scaled_width,
+            letterbox.scaled_height,
+        );
 
         let (original_width, original_height) = (original_img.width(), original_img.height());
-        let resized = resize_rgba(&image, original_width, original_height)?;
+        let resized = resize_rgba(
+            &image,
+            original_width,
+            original_height,
+            self.resize_filter,
+            self.linear_light_resize,
+        )?;
         let img_buffer =
             ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(original_width, original_height, resized)
                 .ok_or(anyhow!("Somehow image was resized to incorrect size"))?;
@@ -91,15 +256,194 @@ impl Rmbg {
 
         Ok(apply_mask(original_img, &mask))
     }
+
+    /// Removes the background from `original_img`, like [`Rmbg::remove_background`], then
+    /// composites the cut-out subject onto `target` instead of leaving it on transparency.
+    ///
+    /// # Arguments
+    ///
+    /// * `original_img` - A reference to the `DynamicImage` to process.
+    /// * `target` - The background to drop the subject onto.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(DynamicImage)` containing the subject composited over `target`.
+    /// Returns `Err(anyhow::Error)` if background removal or compositing fails.
+    pub fn replace_background(
+        &self,
+        original_img: &DynamicImage,
+        target: &CompositeTarget,
+    ) -> anyhow::Result<DynamicImage> {
+        let cutout = self.remove_background(original_img)?;
+        let background = build_background(
+            cutout.width(),
+            cutout.height(),
+            target,
+            self.resize_filter,
+            self.linear_light_resize,
+        )?;
+        Ok(composite_over(&cutout, &background))
+    }
+}
+
+/// Where to drop a segmented subject when replacing its background, via
+/// [`Rmbg::replace_background`].
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CompositeTarget {
+    /// A single solid color, as `[r, g, b]`.
+    Solid { color: [u8; 3] },
+    /// A linear gradient between two colors, along a horizontal or vertical axis.
+    Gradient {
+        direction: GradientDirection,
+        start: [u8; 3],
+        end: [u8; 3],
+    },
+    /// A background image loaded from disk and resized to the subject's dimensions.
+    Image { path: String },
 }
 
-fn preprocess_image(image: &DynamicImage) -> anyhow::Result<Array3<f32>> {
-    let img_vec = resize_rgba(image, ML_MODEL_IMAGE_WIDTH, ML_MODEL_IMAGE_HEIGHT)?;
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GradientDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// Builds a `width` x `height` background image for `target`.
+fn build_background(
+    width: u32,
+    height: u32,
+    target: &CompositeTarget,
+    filter: fr::FilterType,
+    linear_light: bool,
+) -> anyhow::Result<DynamicImage> {
+    match target {
+        CompositeTarget::Solid { color } => {
+            let buf: RgbImage = ImageBuffer::from_pixel(width, height, Rgb(*color));
+            Ok(DynamicImage::ImageRgb8(buf))
+        }
+        CompositeTarget::Gradient {
+            direction,
+            start,
+            end,
+        } => {
+            let mut buf: RgbImage = ImageBuffer::new(width, height);
+            for (x, y, pixel) in buf.enumerate_pixels_mut() {
+                let t = match direction {
+                    GradientDirection::Horizontal => x as f32 / (width.max(2) - 1) as f32,
+                    GradientDirection::Vertical => y as f32 / (height.max(2) - 1) as f32,
+                };
+                *pixel = Rgb([
+                    lerp_channel(start[0], end[0], t),
+                    lerp_channel(start[1], end[1], t),
+                    lerp_channel(start[2], end[2], t),
+                ]);
+            }
+            Ok(DynamicImage::ImageRgb8(buf))
+        }
+        CompositeTarget::Image { path } => {
+            let background = image::open(path)?;
+            let resized = resize_rgba(&background, width, height, filter, linear_light)?;
+            let buf = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(width, height, resized)
+                .ok_or(anyhow!("Somehow background was resized to incorrect size"))?;
+            Ok(DynamicImage::ImageRgba8(buf))
+        }
+    }
+}
+
+fn lerp_channel(start: u8, end: u8, t: f32) -> u8 {
+    (start as f32 + (end as f32 - start as f32) * t).round() as u8
+}
+
+/// Alpha-blends `fg` (whose alpha channel is the subject's mask) over `bg`:
+/// `out = fg * a + bg * (1 - a)`.
+fn composite_over(fg: &DynamicImage, bg: &DynamicImage) -> DynamicImage {
+    let mut out: RgbaImage = ImageBuffer::new(fg.width(), fg.height());
+
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let fg_pixel = fg.get_pixel(x, y);
+        let bg_pixel = bg.get_pixel(x, y);
+        let alpha = fg_pixel[3] as f32 / 255.0;
+
+        let blend =
+            |f: u8, b: u8| -> u8 { (f as f32 * alpha + b as f32 * (1.0 - alpha)).round() as u8 };
+
+        *pixel = Rgba([
+            blend(fg_pixel[0], bg_pixel[0]),
+            blend(fg_pixel[1], bg_pixel[1]),
+            blend(fg_pixel[2], bg_pixel[2]),
+            255,
+        ]);
+    }
+
+    DynamicImage::ImageRgba8(out)
+}
+
+/// Resizes `image` by the largest factor that keeps it within the target canvas while
+/// preserving aspect ratio, then pads the remaining border with black to fill the full target canvas.
+/// Returns the padded RGBA bytes alongside the bookkeeping needed to crop the model's mask
+/// back to the real image region.
+pub(crate) fn letterbox_image(
+    image: &DynamicImage,
+    filter: fr::FilterType,
+    linear_light: bool,
+    target_width: u32,
+    target_height: u32,
+    anchor: LetterboxAnchor,
+) -> anyhow::Result<(Vec<u8>, Letterbox)> {
+    let letterbox = compute_letterbox(
+        image.width(),
+        image.height(),
+        target_width,
+        target_height,
+        anchor,
+    );
+    let scaled = resize_rgba(
+        image,
+        letterbox.scaled_width,
+        letterbox.scaled_height,
+        filter,
+        linear_light,
+    )?;
+
+    let mut canvas: RgbaImage = ImageBuffer::new(target_width, target_height);
+    let scaled_buffer = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(
+        letterbox.scaled_width,
+        letterbox.scaled_height,
+        scaled,
+    )
+    .ok_or(anyhow!("Somehow image was resized to incorrect size"))?;
+    image::imageops::overlay(
+        &mut canvas,
+        &scaled_buffer,
+        letterbox.pad_x as i64,
+        letterbox.pad_y as i64,
+    );
+
+    Ok((canvas.into_raw(), letterbox))
+}
+
+fn preprocess_image(
+    image: &DynamicImage,
+    filter: fr::FilterType,
+    linear_light: bool,
+    config: &ModelConfig,
+) -> anyhow::Result<(Array3<f32>, Letterbox)> {
+    let (input_width, input_height) = config.input_size;
+    let (img_vec, letterbox) = letterbox_image(
+        image,
+        filter,
+        linear_light,
+        input_width,
+        input_height,
+        LetterboxAnchor::Center,
+    )?;
 
     // Separate R, G, and B components
-    let mut r_vec = Vec::with_capacity((ML_MODEL_IMAGE_WIDTH * ML_MODEL_IMAGE_HEIGHT) as usize);
-    let mut g_vec = Vec::with_capacity((ML_MODEL_IMAGE_WIDTH * ML_MODEL_IMAGE_HEIGHT) as usize);
-    let mut b_vec = Vec::with_capacity((ML_MODEL_IMAGE_WIDTH * ML_MODEL_IMAGE_HEIGHT) as usize);
+    let mut r_vec = Vec::with_capacity((input_width * input_height) as usize);
+    let mut g_vec = Vec::with_capacity((input_width * input_height) as usize);
+    let mut b_vec = Vec::with_capacity((input_width * input_height) as usize);
 
     for chunk in img_vec.chunks(4) {
         r_vec.push(chunk[0]);
@@ -113,11 +457,7 @@ fn preprocess_image(image: &DynamicImage) -> anyhow::Result<Array3<f32>> {
 
     // Convert the resized image to a ndarray.
     let img_ndarray = Array3::from_shape_vec(
-        (
-            3,
-            ML_MODEL_IMAGE_WIDTH as usize,
-            ML_MODEL_IMAGE_HEIGHT as usize,
-        ),
+        (3, input_height as usize, input_width as usize),
         reordered_vec,
     )?;
 
@@ -125,21 +465,29 @@ fn preprocess_image(image: &DynamicImage) -> anyhow::Result<Array3<f32>> {
     let img_float: Array3<f32> = img_ndarray.mapv(|x| x as f32 / 255.0);
 
     // Normalize the image.
-    Ok(normalize_image(&img_float))
+    Ok((
+        normalize_image(&img_float, &config.mean, &config.std),
+        letterbox,
+    ))
 }
 
-fn normalize_image(img: &Array3<f32>) -> Array3<f32> {
-    // The mean and std are applied across the channel dimension.
-    let mean = Array3::from_elem((1, img.shape()[1], img.shape()[2]), 0.5);
-    let std = Array3::from_elem((1, img.shape()[1], img.shape()[2]), 1.0);
+fn normalize_image(img: &Array3<f32>, mean: &[f32; 3], std: &[f32; 3]) -> Array3<f32> {
+    // The mean and std are per-channel; broadcast them across the channel dimension.
+    let mean = Array3::from_shape_vec((3, 1, 1), mean.to_vec()).expect("mean has 3 channels");
+    let std = Array3::from_shape_vec((3, 1, 1), std.to_vec()).expect("std has 3 channels");
 
-    // Broadcasting the mean and std to match img dimensions and applying normalization.
     (img - &mean) / &std
 }
 
 fn postprocess_image(
     model_result: &ArrayView<f32, Dim<[usize; 2]>>,
 ) -> anyhow::Result<DynamicImage> {
+    // The output grid isn't necessarily the same size as the configured input (a strided
+    // segmentation head, for instance, can downsample) — read it straight off the tensor
+    // instead of assuming it matches `ModelConfig::input_size`.
+    let (height, width) = model_result.dim();
+    let (width, height) = (width as u32, height as u32);
+
     let ma = model_result
         .iter()
         .max_by(|a, b| a.partial_cmp(b).unwrap())
@@ -152,11 +500,10 @@ fn postprocess_image(
 
     let result_u8 = result.mapv(|x| x as u8).into_raw_vec_and_offset().0;
 
-    let mut imgbuf: ImageBuffer<Rgb<u8>, Vec<u8>> =
-        ImageBuffer::new(ML_MODEL_IMAGE_WIDTH, ML_MODEL_IMAGE_HEIGHT);
+    let mut imgbuf: ImageBuffer<Rgb<u8>, Vec<u8>> = ImageBuffer::new(width, height);
 
     for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
-        let index = (y * ML_MODEL_IMAGE_WIDTH + x) as usize;
+        let index = (y * width + x) as usize;
         let value = result_u8[index];
         *pixel = Rgb([value, value, value]);
     }
@@ -168,6 +515,21 @@ fn resize_rgba(
     img: &DynamicImage,
     target_width: u32,
     target_height: u32,
+    filter: fr::FilterType,
+    linear_light: bool,
+) -> anyhow::Result<Vec<u8>> {
+    if linear_light {
+        resize_rgba_linear(img, target_width, target_height, filter)
+    } else {
+        resize_rgba_srgb(img, target_width, target_height, filter)
+    }
+}
+
+fn resize_rgba_srgb(
+    img: &DynamicImage,
+    target_width: u32,
+    target_height: u32,
+    filter: fr::FilterType,
 ) -> anyhow::Result<Vec<u8>> {
     let mut src_image = fr::images::Image::from_vec_u8(
         img.width(),
@@ -177,6 +539,7 @@ fn resize_rgba(
     )?;
     // Create container for data of destination image
     let mut dst_image = fr::images::Image::new(target_width, target_height, src_image.pixel_type());
+    let resize_options = fr::ResizeOptions::new().resize_alg(fr::ResizeAlg::Convolution(filter));
 
     // Multiple RGB channels of source image by alpha channel
     {
@@ -194,7 +557,7 @@ fn resize_rgba(
         // Create Resizer instance and resize source image
         // into buffer of destination image
         let mut resizer = fr::Resizer::new();
-        resizer.resize_typed(image_mut, &mut dst_view, None)?;
+        resizer.resize_typed(image_mut, &mut dst_view, Some(&resize_options))?;
 
         // Divide RGB channels of destination image by alpha
         alpha_mul_div.divide_alpha_inplace_typed(&mut dst_view)?;
@@ -203,7 +566,83 @@ fn resize_rgba(
     Ok(dst_image.into_vec())
 }
 
-fn apply_mask(original_image: &DynamicImage, mask_image: &DynamicImage) -> DynamicImage {
+/// Like [`resize_rgba_srgb`], but blends in linear light instead of directly in sRGB space:
+/// each 8-bit channel is linearized, premultiplied, resized, and unpremultiplied as `f32`,
+/// then converted back with the inverse sRGB curve. Avoids the darkening/haloing that
+/// resizing gamma-encoded pixels produces around high-contrast edges.
+fn resize_rgba_linear(
+    img: &DynamicImage,
+    target_width: u32,
+    target_height: u32,
+    filter: fr::FilterType,
+) -> anyhow::Result<Vec<u8>> {
+    let rgba = img.to_rgba8();
+    let mut linear_bytes = Vec::with_capacity(rgba.len() * 4);
+    for pixel in rgba.pixels() {
+        linear_bytes.extend_from_slice(&srgb_to_linear(pixel[0]).to_le_bytes());
+        linear_bytes.extend_from_slice(&srgb_to_linear(pixel[1]).to_le_bytes());
+        linear_bytes.extend_from_slice(&srgb_to_linear(pixel[2]).to_le_bytes());
+        linear_bytes.extend_from_slice(&(pixel[3] as f32 / 255.0).to_le_bytes());
+    }
+
+    let mut src_image =
+        fr::images::Image::from_vec_u8(img.width(), img.height(), linear_bytes, fr::PixelType::F32x4)?;
+    let mut dst_image = fr::images::Image::new(target_width, target_height, src_image.pixel_type());
+    let resize_options = fr::ResizeOptions::new().resize_alg(fr::ResizeAlg::Convolution(filter));
+
+    {
+        let alpha_mul_div = fr::MulDiv::default();
+        let image_mut = &mut src_image
+            .image_view_mut::<fr::pixels::F32x4>()
+            .ok_or(anyhow!("Image to mut fail"))?;
+        alpha_mul_div.multiply_alpha_inplace_typed(image_mut)?;
+
+        let mut dst_view = dst_image
+            .image_view_mut::<fr::pixels::F32x4>()
+            .ok_or(anyhow!("Image to mut fail"))?;
+
+        let mut resizer = fr::Resizer::new();
+        resizer.resize_typed(image_mut, &mut dst_view, Some(&resize_options))?;
+
+        alpha_mul_div.divide_alpha_inplace_typed(&mut dst_view)?;
+    }
+
+    let linear_out = dst_image.into_vec();
+    let mut out = Vec::with_capacity((target_width * target_height * 4) as usize);
+    for chunk in linear_out.chunks_exact(16) {
+        let r = f32::from_le_bytes(chunk[0..4].try_into().unwrap());
+        let g = f32::from_le_bytes(chunk[4..8].try_into().unwrap());
+        let b = f32::from_le_bytes(chunk[8..12].try_into().unwrap());
+        let a = f32::from_le_bytes(chunk[12..16].try_into().unwrap());
+        out.push(linear_to_srgb(r));
+        out.push(linear_to_srgb(g));
+        out.push(linear_to_srgb(b));
+        out.push((a.clamp(0.0, 1.0) * 255.0).round() as u8);
+    }
+
+    Ok(out)
+}
+
+fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c < 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(channel: f32) -> u8 {
+    let c = channel.clamp(0.0, 1.0);
+    let srgb = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round() as u8
+}
+
+pub(crate) fn apply_mask(original_image: &DynamicImage, mask_image: &DynamicImage) -> DynamicImage {
     // Create a new transparent image
     let mut no_bg_image: RgbaImage = ImageBuffer::new(mask_image.width(), mask_image.height());
 
@@ -237,4 +676,71 @@ mod tests {
             .save_with_format("assets/no_bg.webp", ImageFormat::WebP)
             .unwrap();
     }
+
+    #[test]
+    fn letterbox_fits_within_non_square_target() {
+        // A wide source into a tall target: scaling against the tighter axis (width) must
+        // keep the scaled image within both target dimensions, so the pad subtraction can't
+        // underflow.
+        let letterbox = compute_letterbox(2000, 1000, 512, 768, LetterboxAnchor::Center);
+
+        assert!(letterbox.scaled_width <= 512);
+        assert!(letterbox.scaled_height <= 768);
+    }
+
+    #[test]
+    fn letterbox_centers_into_square_target() {
+        let letterbox = compute_letterbox(2000, 1000, 1024, 1024, LetterboxAnchor::Center);
+
+        assert_eq!(letterbox.scaled_width, 1024);
+        assert_eq!(letterbox.scaled_height, 512);
+        assert_eq!(letterbox.pad_x, 0);
+        assert_eq!(letterbox.pad_y, 256);
+    }
+
+    #[test]
+    fn letterbox_top_left_anchor_pads_bottom_right_only() {
+        let letterbox = compute_letterbox(2000, 1000, 1024, 1024, LetterboxAnchor::TopLeft);
+
+        assert_eq!(letterbox.scaled_width, 1024);
+        assert_eq!(letterbox.scaled_height, 512);
+        assert_eq!(letterbox.pad_x, 0);
+        assert_eq!(letterbox.pad_y, 0);
+    }
+
+    #[test]
+    fn srgb_linear_round_trip_is_lossless_at_8_bit() {
+        for channel in 0..=255u8 {
+            assert_eq!(linear_to_srgb(srgb_to_linear(channel)), channel);
+        }
+    }
+
+    #[test]
+    fn lerp_channel_interpolates_linearly() {
+        assert_eq!(lerp_channel(0, 255, 0.0), 0);
+        assert_eq!(lerp_channel(0, 255, 0.5), 128);
+        assert_eq!(lerp_channel(0, 255, 1.0), 255);
+        assert_eq!(lerp_channel(255, 0, 0.5), 128);
+    }
+
+    #[test]
+    fn composite_over_blends_by_alpha() {
+        let mut fg: RgbaImage = ImageBuffer::new(3, 1);
+        fg.put_pixel(0, 0, Rgba([200, 0, 0, 0]));
+        fg.put_pixel(1, 0, Rgba([200, 0, 0, 255]));
+        fg.put_pixel(2, 0, Rgba([200, 0, 0, 128]));
+        let fg = DynamicImage::ImageRgba8(fg);
+
+        let bg: RgbaImage = ImageBuffer::from_pixel(3, 1, Rgba([0, 100, 0, 255]));
+        let bg = DynamicImage::ImageRgba8(bg);
+
+        let out = composite_over(&fg, &bg);
+
+        // alpha=0: fully background.
+        assert_eq!(out.get_pixel(0, 0), Rgba([0, 100, 0, 255]));
+        // alpha=255: fully foreground.
+        assert_eq!(out.get_pixel(1, 0), Rgba([200, 0, 0, 255]));
+        // alpha=128 (~0.502): about half foreground red, about half background green.
+        assert_eq!(out.get_pixel(2, 0), Rgba([100, 50, 0, 255]));
+    }
 }
@@ -0,0 +1,269 @@
+use crate::rmbg::{apply_mask, compute_letterbox, letterbox_image, Letterbox, LetterboxAnchor};
+use anyhow::anyhow;
+use fast_image_resize::FilterType;
+use image::{DynamicImage, ImageBuffer, Luma};
+use ndarray::{s, Array1, Array2, Array3, Array4, ArrayView, Axis, Dim};
+use serde::Deserialize;
+use std::path::Path;
+
+const SAM_ENCODER_INPUT_NAME: &str = "images";
+const SAM_ENCODER_OUTPUT_NAME: &str = "image_embeddings";
+const SAM_DECODER_OUTPUT_NAME: &str = "masks";
+const SAM_MASK_INPUT_SIZE: usize = 256;
+// SAM's encoder always takes a fixed 1024x1024 input, independent of `Rmbg`'s configurable
+// model size.
+const SAM_INPUT_WIDTH: u32 = 1024;
+const SAM_INPUT_HEIGHT: u32 = 1024;
+
+// The official Segment Anything preprocessing normalizes 0-255 pixels with ImageNet-style
+// per-channel mean/std, unlike RMBG's 0-1 scalar normalization.
+const SAM_PIXEL_MEAN: [f32; 3] = [123.675, 116.28, 103.53];
+const SAM_PIXEL_STD: [f32; 3] = [58.395, 57.12, 57.375];
+
+/// A foreground or background click point, in original-image pixel coordinates.
+#[derive(Deserialize)]
+pub struct PromptPoint {
+    pub x: f32,
+    pub y: f32,
+    pub foreground: bool,
+}
+
+/// A bounding box around the subject, in original-image pixel coordinates.
+#[derive(Deserialize)]
+pub struct PromptBox {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+/// The set of clicks and/or a box a user gave to point at the subject they want segmented.
+#[derive(Default, Deserialize)]
+pub struct Prompt {
+    #[serde(default)]
+    pub points: Vec<PromptPoint>,
+    #[serde(default)]
+    pub bbox: Option<PromptBox>,
+}
+
+/// A Segment Anything-style interactive segmenter: an image encoder that embeds the picture
+/// once, and a lightweight mask decoder that turns point/box prompts into a mask cheaply.
+///
+/// This mirrors [`crate::rmbg::Rmbg`] in shape but takes a prompt instead of running fully
+/// automatic detection, so users can click the subject they want cut out.
+///
+/// Each call to [`Sam::segment_with_prompt`] runs the encoder on the image again; embeddings
+/// are not kept between calls.
+pub struct Sam {
+    encoder: ort::Session,
+    decoder: ort::Session,
+}
+
+impl Sam {
+    /// Constructs a new `Sam` instance from an encoder/decoder ONNX pair exported in the
+    /// standard Segment Anything onnx layout (image encoder + prompt-conditioned decoder).
+    pub fn new(
+        encoder_path: impl AsRef<Path>,
+        decoder_path: impl AsRef<Path>,
+    ) -> Result<Self, ort::Error> {
+        let encoder = ort::Session::builder()?.commit_from_file(encoder_path)?;
+        let decoder = ort::Session::builder()?.commit_from_file(decoder_path)?;
+        Ok(Sam { encoder, decoder })
+    }
+
+    /// Segments `original_img` according to `prompt`, returning an RGBA cut-out the same way
+    /// [`crate::rmbg::Rmbg::remove_background`] does.
+    ///
+    /// Prompt coordinates are given in `original_img`'s own pixel space; they're mapped into
+    /// the encoder's 1024x1024 input space using the same letterbox scale/offset bookkeeping
+    /// that `Rmbg` uses for preprocessing, anchored top-left (where `Rmbg` centers) to match
+    /// SAM's own preprocessing.
+    pub fn segment_with_prompt(
+        &self,
+        original_img: &DynamicImage,
+        prompt: &Prompt,
+    ) -> anyhow::Result<DynamicImage> {
+        if prompt.points.is_empty() && prompt.bbox.is_none() {
+            return Err(anyhow!("Prompt must contain at least one point or a box"));
+        }
+
+        let letterbox = compute_letterbox(
+            original_img.width(),
+            original_img.height(),
+            SAM_INPUT_WIDTH,
+            SAM_INPUT_HEIGHT,
+            LetterboxAnchor::TopLeft,
+        );
+        let embeddings = self.embed_image(original_img)?;
+        let (point_coords, point_labels) = prompt_to_model_space(prompt, &letterbox);
+
+        let mask_input = Array4::<f32>::zeros((1, 1, SAM_MASK_INPUT_SIZE, SAM_MASK_INPUT_SIZE));
+        let has_mask_input = Array1::<f32>::zeros(1);
+        let orig_im_size =
+            Array1::from_vec(vec![original_img.height() as f32, original_img.width() as f32]);
+
+        let decoder_inputs = ort::inputs![
+            "image_embeddings" => embeddings.view(),
+            "point_coords" => point_coords.view(),
+            "point_labels" => point_labels.view(),
+            "mask_input" => mask_input.view(),
+            "has_mask_input" => has_mask_input.view(),
+            "orig_im_size" => orig_im_size.view(),
+        ]?;
+        let decoder_outputs = self.decoder.run(decoder_inputs)?;
+
+        let masks = decoder_outputs[SAM_DECODER_OUTPUT_NAME].try_extract_tensor::<f32>()?;
+        let view = masks.view();
+        let mask: ArrayView<f32, Dim<[usize; 2]>> = view.slice(s![0, 0, .., ..]);
+
+        let mask_image = mask_to_image(&mask)?;
+        Ok(apply_mask(original_img, &mask_image))
+    }
+
+    fn embed_image(&self, original_img: &DynamicImage) -> anyhow::Result<Array3<f32>> {
+        let img = preprocess_for_sam(original_img)?;
+        let input = img.insert_axis(Axis(0));
+        let inputs = ort::inputs![SAM_ENCODER_INPUT_NAME => input.view()]?;
+        let outputs = self.encoder.run(inputs)?;
+        let embeddings = outputs[SAM_ENCODER_OUTPUT_NAME].try_extract_tensor::<f32>()?;
+        Ok(embeddings.view().slice(s![0, .., .., ..]).to_owned())
+    }
+}
+
+fn preprocess_for_sam(image: &DynamicImage) -> anyhow::Result<Array3<f32>> {
+    // Real Segment Anything ONNX exports resize with `ResizeLongestSide` and pad only the
+    // bottom/right (no centering), so prompts and masks line up with what the network was
+    // trained on.
+    let (img_vec, _letterbox) = letterbox_image(
+        image,
+        FilterType::Bilinear,
+        false,
+        SAM_INPUT_WIDTH,
+        SAM_INPUT_HEIGHT,
+        LetterboxAnchor::TopLeft,
+    )?;
+
+    let mut channels: [Vec<f32>; 3] = Default::default();
+    for chunk in img_vec.chunks(4) {
+        for (c, channel) in channels.iter_mut().enumerate() {
+            channel.push((chunk[c] as f32 - SAM_PIXEL_MEAN[c]) / SAM_PIXEL_STD[c]);
+        }
+        // SKIP alpha channel
+    }
+
+    let [r, g, b] = channels;
+    let reordered = [r, g, b].concat();
+    Ok(Array3::from_shape_vec(
+        (3, SAM_INPUT_HEIGHT as usize, SAM_INPUT_WIDTH as usize),
+        reordered,
+    )?)
+}
+
+/// Maps a [`Prompt`]'s points and box from original-image coordinates into the encoder's
+/// model-space coordinates, in the `(point_coords, point_labels)` layout the SAM decoder
+/// expects: label `1` for a foreground point, `0` for background, `2`/`3` for a box's
+/// top-left/bottom-right corners.
+///
+/// When there's no box, the standard SAM ONNX decoder export still expects a padding point
+/// at `(0, 0)` with label `-1` appended after the real points, signalling "no box" to the
+/// model; without it the decoder has no way to distinguish "no box" from a real prompt and
+/// the mask comes out wrong. The box's own two corners already give the model that signal,
+/// so the pad point is only needed in the points-only case.
+fn prompt_to_model_space(prompt: &Prompt, letterbox: &Letterbox) -> (Array3<f32>, Array2<f32>) {
+    let to_model_space = |x: f32, y: f32| {
+        (
+            x * letterbox.scale + letterbox.pad_x as f32,
+            y * letterbox.scale + letterbox.pad_y as f32,
+        )
+    };
+
+    let mut coords = Vec::new();
+    let mut labels = Vec::new();
+
+    for point in &prompt.points {
+        let (x, y) = to_model_space(point.x, point.y);
+        coords.push(x);
+        coords.push(y);
+        labels.push(if point.foreground { 1.0 } else { 0.0 });
+    }
+
+    if let Some(bbox) = &prompt.bbox {
+        let (x0, y0) = to_model_space(bbox.x0, bbox.y0);
+        let (x1, y1) = to_model_space(bbox.x1, bbox.y1);
+        coords.extend([x0, y0, x1, y1]);
+        labels.extend([2.0, 3.0]);
+    } else {
+        coords.extend([0.0, 0.0]);
+        labels.push(-1.0);
+    }
+
+    let num_prompts = labels.len();
+    let point_coords = Array3::from_shape_vec((1, num_prompts, 2), coords)
+        .expect("coords and labels are built together with matching lengths");
+    let point_labels = Array2::from_shape_vec((1, num_prompts), labels)
+        .expect("coords and labels are built together with matching lengths");
+
+    (point_coords, point_labels)
+}
+
+fn mask_to_image(mask: &ArrayView<f32, Dim<[usize; 2]>>) -> anyhow::Result<DynamicImage> {
+    let (height, width) = mask.dim();
+    let mut imgbuf: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::new(width as u32, height as u32);
+
+    for (x, y, pixel) in imgbuf.enumerate_pixels_mut() {
+        // SAM decoder masks are logits; a pixel is foreground wherever the logit is positive.
+        let logit = mask[[y as usize, x as usize]];
+        *pixel = Luma([if logit > 0.0 { 255 } else { 0 }]);
+    }
+
+    Ok(DynamicImage::ImageLuma8(imgbuf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity_letterbox() -> Letterbox {
+        compute_letterbox(
+            SAM_INPUT_WIDTH,
+            SAM_INPUT_HEIGHT,
+            SAM_INPUT_WIDTH,
+            SAM_INPUT_HEIGHT,
+            LetterboxAnchor::TopLeft,
+        )
+    }
+
+    #[test]
+    fn points_only_prompt_gets_a_no_box_padding_point() {
+        let prompt = Prompt {
+            points: vec![PromptPoint {
+                x: 10.0,
+                y: 20.0,
+                foreground: true,
+            }],
+            bbox: None,
+        };
+
+        let (coords, labels) = prompt_to_model_space(&prompt, &identity_letterbox());
+
+        assert_eq!(labels.as_slice().unwrap(), &[1.0, -1.0]);
+        assert_eq!(coords.as_slice().unwrap(), &[10.0, 20.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn bbox_prompt_has_no_padding_point() {
+        let prompt = Prompt {
+            points: vec![],
+            bbox: Some(PromptBox {
+                x0: 1.0,
+                y0: 2.0,
+                x1: 3.0,
+                y1: 4.0,
+            }),
+        };
+
+        let (_coords, labels) = prompt_to_model_space(&prompt, &identity_letterbox());
+
+        assert_eq!(labels.as_slice().unwrap(), &[2.0, 3.0]);
+    }
+}